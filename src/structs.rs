@@ -1,18 +1,77 @@
 use actions::*;
 use chrono::prelude::*;
-use rayon::prelude::*;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use reqwest;
+use reqwest::header::{ByteRangeSpec, ContentLength, Range};
+#[cfg(feature = "ytdlp")]
+use reqwest::header::ContentType;
+use reqwest::StatusCode;
 use rss::{self, Channel, Item};
+use rusqlite::Connection;
 use serde_json;
-use std::collections::BTreeSet;
-use std::fs::{self, DirBuilder, File};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::fs::{self, DirBuilder, File, OpenOptions};
+use std::hash::{Hash, Hasher};
 use std::io::{self, Read, Write};
+#[cfg(feature = "ytdlp")]
+use std::process::Command;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
 use utils::*;
 use yaml_rust::YamlLoader;
 
+/// Result of resolving an episode's real media URL via the optional
+/// `yt-dlp` backend (see `ytdlp_lookup`), for feeds whose enclosure points
+/// at a page or video host rather than a direct audio file.
+#[cfg(feature = "ytdlp")]
+#[allow(dead_code)]
+struct YtDlpInfo {
+    url: String,
+    ext: String,
+    title: String,
+    duration: Option<f64>,
+}
+
+/// Whether a response looks like it's actually audio, based on its
+/// `Content-Type`. Missing headers are assumed to be audio so feeds that
+/// simply don't set one aren't needlessly routed through `yt-dlp`.
+#[cfg(feature = "ytdlp")]
+fn is_audio_response(resp: &reqwest::Response) -> bool {
+    resp.headers()
+        .get::<ContentType>()
+        .map(|content_type| content_type.to_string().starts_with("audio/"))
+        .unwrap_or(true)
+}
+
+/// Shell out to `yt-dlp --dump-json` to resolve the direct media URL behind
+/// a page/video-host enclosure, selecting `format` (e.g. `"bestaudio"`).
+#[cfg(feature = "ytdlp")]
+fn ytdlp_lookup(url: &str, format: &str) -> Option<YtDlpInfo> {
+    let output = Command::new("yt-dlp")
+        .arg("--dump-json")
+        .arg("-f")
+        .arg(format)
+        .arg(url)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let value: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    Some(YtDlpInfo {
+        url: value["url"].as_str()?.to_string(),
+        ext: value["ext"].as_str().unwrap_or("mp3").to_string(),
+        title: value["title"].as_str().unwrap_or("").to_string(),
+        duration: value["duration"].as_f64(),
+    })
+}
+
 pub struct Config {
     pub auto_download_limit: i64,
     pub auto_delete_limit: i64,
+    pub max_concurrent_downloads: usize,
 }
 
 impl Config {
@@ -20,6 +79,7 @@ impl Config {
         let mut path = get_podcast_dir();
         let mut download_limit = 1;
         let mut delete_limit = 0;
+        let mut max_concurrent_downloads = 3;
         path.push(".config");
         if path.exists() {
             let mut s = String::new();
@@ -33,6 +93,9 @@ impl Config {
                 if let Some(val) = doc["auto_delete_limit"].as_i64() {
                     delete_limit = val;
                 }
+                if let Some(val) = doc["max_concurrent_downloads"].as_i64() {
+                    max_concurrent_downloads = val as usize;
+                }
             }
         } else {
             let mut file = File::create(&path).unwrap();
@@ -41,16 +104,320 @@ impl Config {
         Config {
             auto_download_limit: download_limit,
             auto_delete_limit: delete_limit,
+            max_concurrent_downloads: if max_concurrent_downloads == 0 {
+                1
+            } else {
+                max_concurrent_downloads
+            },
+        }
+    }
+}
+
+
+/// Filesystem-safe length cap well under common `NAME_MAX` limits, leaving
+/// room for an extension and a disambiguating suffix.
+const MAX_FILENAME_LEN: usize = 150;
+
+/// Strip path separators, null bytes, and other OS-illegal characters from
+/// an episode title so it's safe to use as a filename, and cap its length.
+fn sanitize_filename(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    sanitized = sanitized.trim().trim_end_matches('.').to_string();
+    if sanitized.is_empty() {
+        sanitized = String::from("untitled");
+    }
+    let mut end = sanitized.len().min(MAX_FILENAME_LEN);
+    while !sanitized.is_char_boundary(end) {
+        end -= 1;
+    }
+    sanitized.truncate(end);
+    sanitized
+}
+
+/// A short, stable suffix to disambiguate two episodes that sanitize down
+/// to the same filename: the episode's publish date if it has one,
+/// otherwise a hash of its guid.
+fn disambiguating_suffix(guid: &str, pub_date: &str) -> String {
+    if !pub_date.trim().is_empty() {
+        return sanitize_filename(pub_date);
+    }
+    let mut hasher = DefaultHasher::new();
+    guid.hash(&mut hasher);
+    format!("{:x}", hasher.finish())[..8].to_string()
+}
+
+/// Swallow the "duplicate column name" error from a best-effort `ALTER
+/// TABLE ... ADD COLUMN` migration; surface any other failure.
+fn ignore_existing(result: Result<usize, rusqlite::Error>) -> Result<(), rusqlite::Error> {
+    match result {
+        Ok(_) => Ok(()),
+        Err(rusqlite::Error::SqliteFailure(_, Some(ref msg)))
+            if msg.contains("duplicate column name") =>
+        {
+            Ok(())
         }
+        Err(err) => Err(err),
     }
 }
 
+/// Per-episode memory backed by SQLite: which episodes exist, which have
+/// been downloaded, and how far the user has listened.
+pub struct Database {
+    conn: Connection,
+}
+
+impl Database {
+    pub fn new() -> Result<Database, rusqlite::Error> {
+        let mut path = get_podcast_dir();
+        path.push(".podcast.db");
+        let conn = Connection::open(&path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS podcasts (
+                 id    INTEGER PRIMARY KEY,
+                 title TEXT NOT NULL UNIQUE,
+                 url   TEXT NOT NULL
+             );
+             CREATE TABLE IF NOT EXISTS episodes (
+                 id          INTEGER PRIMARY KEY,
+                 podcast_id  INTEGER NOT NULL REFERENCES podcasts(id),
+                 guid        TEXT NOT NULL,
+                 title       TEXT NOT NULL,
+                 pub_date    TEXT NOT NULL DEFAULT '',
+                 downloaded  INTEGER NOT NULL DEFAULT 0,
+                 played      INTEGER NOT NULL DEFAULT 0,
+                 last_position INTEGER NOT NULL DEFAULT 0,
+                 keep        INTEGER NOT NULL DEFAULT 0,
+                 filename    TEXT,
+                 UNIQUE(podcast_id, guid)
+             );",
+        )?;
+        // Databases created before earlier columns were added won't have
+        // them yet; add them and ignore the "duplicate column" error.
+        ignore_existing(conn.execute(
+            "ALTER TABLE episodes ADD COLUMN pub_date TEXT NOT NULL DEFAULT ''",
+            &[],
+        ))?;
+        ignore_existing(conn.execute(
+            "ALTER TABLE episodes ADD COLUMN keep INTEGER NOT NULL DEFAULT 0",
+            &[],
+        ))?;
+        ignore_existing(conn.execute("ALTER TABLE episodes ADD COLUMN filename TEXT", &[]))?;
+        Ok(Database { conn })
+    }
+
+    pub fn upsert_podcast(&self, title: &str, url: &str) -> Result<i64, rusqlite::Error> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO podcasts (title, url) VALUES (?1, ?2)",
+            &[&title, &url],
+        )?;
+        self.conn.query_row(
+            "SELECT id FROM podcasts WHERE title = ?1",
+            &[&title],
+            |row| row.get(0),
+        )
+    }
+
+    /// Diff `episodes` against the stored rows for `podcast_title`, inserting
+    /// any rows that aren't already known, and return only the new ones.
+    pub fn sync_episodes(
+        &self,
+        podcast_title: &str,
+        podcast_url: &str,
+        episodes: &[Episode],
+    ) -> Result<Vec<Episode>, rusqlite::Error> {
+        let podcast_id = self.upsert_podcast(podcast_title, podcast_url)?;
+        let mut fresh = Vec::new();
+        for episode in episodes {
+            let guid = episode.guid();
+            let title = episode.title().unwrap_or("");
+            let pub_date = episode.pub_date().unwrap_or("");
+            // Stored as UTC RFC 3339 so `ORDER BY pub_date` is chronological;
+            // dates that don't parse as RFC 2822 are stored as given.
+            let pub_date = DateTime::parse_from_rfc2822(pub_date)
+                .map(|date| date.with_timezone(&Utc).to_rfc3339())
+                .unwrap_or_else(|_| String::from(pub_date));
+            let inserted = self.conn.execute(
+                "INSERT OR IGNORE INTO episodes (podcast_id, guid, title, pub_date)
+                 VALUES (?1, ?2, ?3, ?4)",
+                &[&podcast_id, &guid, &title, &pub_date],
+            )?;
+            if inserted > 0 {
+                fresh.push(episode.clone());
+            }
+        }
+        Ok(fresh)
+    }
+
+    /// The newest `limit` not-yet-downloaded episode guids for a podcast,
+    /// for the auto-download side of the retention engine.
+    pub fn pending_download(
+        &self,
+        podcast_title: &str,
+        limit: i64,
+    ) -> Result<Vec<String>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT e.guid FROM episodes e
+             JOIN podcasts p ON p.id = e.podcast_id
+             WHERE p.title = ?1 AND e.downloaded = 0
+             ORDER BY e.pub_date DESC
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(&[&podcast_title, &limit], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    /// Downloaded, not-`keep`-flagged episodes beyond the newest `limit`,
+    /// for the auto-delete side of the retention engine. Returns each
+    /// episode's guid, title, and persisted on-disk `filename` (if one was
+    /// ever reserved for it) so callers can reconstruct the real path
+    /// instead of re-deriving it from the (possibly since-reformatted) raw
+    /// title.
+    pub fn downloaded_overflow(
+        &self,
+        podcast_title: &str,
+        limit: i64,
+    ) -> Result<Vec<(String, String, Option<String>)>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT guid, title, filename FROM episodes e
+             JOIN podcasts p ON p.id = e.podcast_id
+             WHERE p.title = ?1 AND e.downloaded = 1 AND e.keep = 0
+             ORDER BY e.pub_date DESC",
+        )?;
+        let mut rows: Vec<(String, String, Option<String>)> = stmt
+            .query_map(&[&podcast_title], |row| (row.get(0), row.get(1), row.get(2)))?
+            .collect::<Result<_, _>>()?;
+        if (rows.len() as i64) > limit {
+            Ok(rows.split_off(limit as usize))
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    pub fn set_keep(&self, guid: &str, keep: bool) -> Result<(), rusqlite::Error> {
+        self.conn.execute(
+            "UPDATE episodes SET keep = ?2 WHERE guid = ?1",
+            &[&guid, &(keep as i64)],
+        )?;
+        Ok(())
+    }
+
+    pub fn mark_deleted(&self, guid: &str) -> Result<(), rusqlite::Error> {
+        self.conn
+            .execute("UPDATE episodes SET downloaded = 0 WHERE guid = ?1", &[&guid])?;
+        Ok(())
+    }
+
+    /// The on-disk filename (sans extension) previously reserved for this
+    /// episode, if any.
+    pub fn filename_for(&self, podcast_title: &str, guid: &str) -> Option<String> {
+        self.conn
+            .query_row(
+                "SELECT filename FROM episodes e
+                 JOIN podcasts p ON p.id = e.podcast_id
+                 WHERE p.title = ?1 AND e.guid = ?2 AND filename IS NOT NULL",
+                &[&podcast_title, &guid],
+                |row| row.get(0),
+            )
+            .ok()
+    }
+
+    /// Claim `candidate` as this episode's on-disk filename, disambiguating
+    /// it against any other episode of the same podcast already using it,
+    /// and persist the result so future runs reuse the same filename even
+    /// if the feed reformats the episode's title.
+    pub fn reserve_filename(
+        &self,
+        podcast_title: &str,
+        guid: &str,
+        candidate: &str,
+        suffix: &str,
+    ) -> Result<String, rusqlite::Error> {
+        let taken: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM episodes e
+             JOIN podcasts p ON p.id = e.podcast_id
+             WHERE p.title = ?1 AND e.filename = ?2 AND e.guid != ?3",
+            &[&podcast_title, &candidate, &guid],
+            |row| row.get(0),
+        )?;
+        let resolved = if taken > 0 {
+            format!("{}-{}", candidate, suffix)
+        } else {
+            String::from(candidate)
+        };
+        self.conn.execute(
+            "UPDATE episodes SET filename = ?3
+             WHERE guid = ?2 AND podcast_id = (SELECT id FROM podcasts WHERE title = ?1)",
+            &[&podcast_title, &guid, &resolved],
+        )?;
+        Ok(resolved)
+    }
+
+    pub fn new_episodes(&self, podcast_title: &str) -> Result<Vec<String>, rusqlite::Error> {
+        let mut stmt = self.conn.prepare(
+            "SELECT e.guid FROM episodes e
+             JOIN podcasts p ON p.id = e.podcast_id
+             WHERE p.title = ?1 AND e.downloaded = 0",
+        )?;
+        let rows = stmt.query_map(&[&podcast_title], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    pub fn is_downloaded(&self, podcast_title: &str, guid: &str) -> bool {
+        self.conn
+            .query_row(
+                "SELECT downloaded FROM episodes e
+                 JOIN podcasts p ON p.id = e.podcast_id
+                 WHERE p.title = ?1 AND e.guid = ?2",
+                &[&podcast_title, &guid],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|downloaded| downloaded != 0)
+            .unwrap_or(false)
+    }
+
+    pub fn mark_downloaded(&self, podcast_title: &str, guid: &str) -> Result<(), rusqlite::Error> {
+        self.conn.execute(
+            "UPDATE episodes SET downloaded = 1
+             WHERE guid = ?2 AND podcast_id = (SELECT id FROM podcasts WHERE title = ?1)",
+            &[&podcast_title, &guid],
+        )?;
+        Ok(())
+    }
+
+    pub fn mark_played(&self, guid: &str) -> Result<(), rusqlite::Error> {
+        self.conn
+            .execute("UPDATE episodes SET played = 1 WHERE guid = ?1", &[&guid])?;
+        Ok(())
+    }
+
+    pub fn set_position(&self, guid: &str, position: i64) -> Result<(), rusqlite::Error> {
+        self.conn.execute(
+            "UPDATE episodes SET last_position = ?2 WHERE guid = ?1",
+            &[&guid, &position],
+        )?;
+        Ok(())
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Subscription {
     pub title: String,
     pub url: String,
     pub num_episodes: usize,
+    /// Per-feed override of `Config::auto_download_limit`; falls back to
+    /// the global default when unset.
+    #[serde(default)]
+    pub download_limit: Option<i64>,
+    /// Per-feed override of `Config::auto_delete_limit`.
+    #[serde(default)]
+    pub delete_limit: Option<i64>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -89,6 +456,8 @@ impl State {
                 .num_seconds() < -86400
             {
                 update_rss(&mut state);
+                state.sync_databases();
+                state.enforce_retention(&Config::new());
             }
             state.last_run_time = Utc::now();
             Ok(state)
@@ -111,8 +480,15 @@ impl State {
                 title: String::from(podcast.title()),
                 url: String::from(url),
                 num_episodes: podcast.episodes().len(),
+                download_limit: None,
+                delete_limit: None,
             });
         }
+        if let Ok(db) = Database::new() {
+            if let Err(err) = db.sync_episodes(podcast.title(), url, &podcast.episodes()) {
+                eprintln!("{}", err);
+            }
+        }
         if let Err(err) = self.save() {
             eprintln!("{}", err);
         }
@@ -123,6 +499,52 @@ impl State {
         self.subs.clone()
     }
 
+    /// Mirror every subscription's episodes into the SQLite database.
+    /// `update_rss` only refreshes `self.subs` in memory on the daily
+    /// refresh path, so this keeps the on-disk episode table (and thus
+    /// `new_episodes`/`is_downloaded`/the retention queries) in sync with
+    /// episodes that show up via that periodic refresh rather than an
+    /// explicit `subscribe()` call.
+    fn sync_databases(&self) {
+        let db = match Database::new() {
+            Ok(db) => db,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        };
+        for sub in &self.subs {
+            let podcast = match Podcast::from_url(&sub.url) {
+                Ok(val) => val,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    continue;
+                }
+            };
+            if let Err(err) = db.sync_episodes(&sub.title, &sub.url, &podcast.episodes()) {
+                eprintln!("{}", err);
+            }
+        }
+    }
+
+    /// Auto-download the newest not-yet-downloaded episodes and auto-delete
+    /// the oldest downloaded ones for every subscription, honoring each
+    /// feed's own `download_limit`/`delete_limit` override.
+    pub fn enforce_retention(&self, config: &Config) {
+        for sub in &self.subs {
+            let podcast = match Podcast::from_url(&sub.url) {
+                Ok(val) => val,
+                Err(err) => {
+                    eprintln!("{}", err);
+                    continue;
+                }
+            };
+            let download_limit = sub.download_limit.unwrap_or(config.auto_download_limit);
+            let delete_limit = sub.delete_limit.unwrap_or(config.auto_delete_limit);
+            podcast.enforce_retention(download_limit, delete_limit);
+        }
+    }
+
     pub fn save(&self) -> Result<(), io::Error> {
         let mut path = get_podcast_dir();
         path.push(".subscriptions.tmp");
@@ -157,7 +579,6 @@ impl Podcast {
         self.0.title()
     }
 
-    #[allow(dead_code)]
     pub fn url(&self) -> &str {
         self.0.link()
     }
@@ -179,43 +600,291 @@ impl Podcast {
         result
     }
 
-    pub fn download(&self) {
-        let mut path = get_podcast_dir();
-        path.push(self.title());
-
+    pub fn download(&self, config: &Config) {
         let downloaded = already_downloaded(self.title());
+        let db = Database::new().ok();
+        let episodes = self.episodes();
+        if let Some(db) = db.as_ref() {
+            // Make sure every current episode has a row before checking
+            // `is_downloaded`/reserving a filename for it below — without
+            // this, an episode that only showed up since the last sync
+            // would silently fail to record its download and get
+            // re-downloaded forever.
+            if let Err(err) = db.sync_episodes(self.title(), self.url(), &episodes) {
+                eprintln!("{}", err);
+            }
+        }
 
-        self.episodes().par_iter().for_each(
-            |ref i| if let Some(ep_title) =
-                i.title()
-            {
-                if !downloaded.contains(ep_title) {
-                    if let Err(err) = i.download(self.title()) {
-                        println!("{}", err);
-                    }
+        let pending: Vec<Episode> = episodes
+            .into_iter()
+            .filter(|i| match i.title() {
+                Some(ep_title) => {
+                    let already_downloaded = match db.as_ref() {
+                        // Identity-based: a reformatted title no longer
+                        // hides an episode that's already on disk.
+                        Some(db) => db.is_downloaded(self.title(), &i.guid()),
+                        None => downloaded.contains(ep_title),
+                    };
+                    !already_downloaded
                 }
-            },
-        );
-    }
+                None => false,
+            })
+            .collect();
 
-    pub fn download_specific(&self, episode_numbers: Vec<usize>) {
-        let mut path = get_podcast_dir();
-        path.push(self.title());
+        let downloader = Downloader::new(config.max_concurrent_downloads);
+        let failures = downloader.run(self.title(), pending, db.as_ref());
+        for (title, err) in failures {
+            println!("Failed to download {}: {}", title, err);
+        }
+    }
 
+    pub fn download_specific(&self, episode_numbers: Vec<usize>, config: &Config) {
         let downloaded = already_downloaded(self.title());
+        let db = Database::new().ok();
+        let episodes = self.episodes();
+        if let Some(db) = db.as_ref() {
+            if let Err(err) = db.sync_episodes(self.title(), self.url(), &episodes) {
+                eprintln!("{}", err);
+            }
+        }
+
+        let pending: Vec<Episode> = episode_numbers
+            .iter()
+            .map(|ep_num| episodes[episodes.len() - ep_num].clone())
+            .filter(|episode| match episode.title() {
+                Some(ep_title) => {
+                    let already_downloaded = match db.as_ref() {
+                        Some(db) => db.is_downloaded(self.title(), &episode.guid()),
+                        None => downloaded.contains(ep_title),
+                    };
+                    !already_downloaded
+                }
+                None => false,
+            })
+            .collect();
+
+        let downloader = Downloader::new(config.max_concurrent_downloads);
+        let failures = downloader.run(self.title(), pending, db.as_ref());
+        for (title, err) in failures {
+            println!("Failed to download {}: {}", title, err);
+        }
+    }
+
+    /// Enforce the rolling-window retention policy for this podcast: pull
+    /// in the newest `download_limit` not-yet-downloaded episodes, then
+    /// trim downloaded episodes back down to `delete_limit`, oldest first,
+    /// skipping any flagged to `keep`. A `delete_limit` of 0 or less turns
+    /// auto-deletion off.
+    pub fn enforce_retention(&self, download_limit: i64, delete_limit: i64) {
+        let db = match Database::new() {
+            Ok(val) => val,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        };
         let episodes = self.episodes();
 
-        episode_numbers.par_iter().for_each(
-            |ep_num| if let Some(ep_title) =
-                episodes[episodes.len() - ep_num].title()
-            {
-                if !downloaded.contains(ep_title) {
-                    if let Err(err) = episodes[episodes.len() - ep_num].download(self.title()) {
-                        println!("{}", err);
+        let pending = match db.pending_download(self.title(), download_limit) {
+            Ok(val) => val,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        };
+        for guid in pending {
+            if let Some(episode) = episodes.iter().find(|e| e.guid() == guid) {
+                match episode.download(self.title()) {
+                    Ok(()) => if let Err(err) = db.mark_downloaded(self.title(), &guid) {
+                        eprintln!("{}", err);
+                    },
+                    Err(err) => println!("{}", err),
+                }
+            }
+        }
+
+        if delete_limit <= 0 {
+            return;
+        }
+        let overflow = match db.downloaded_overflow(self.title(), delete_limit) {
+            Ok(val) => val,
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        };
+        for (guid, title, filename) in overflow {
+            let extension = episodes
+                .iter()
+                .find(|e| e.guid() == guid)
+                .and_then(|e| e.extension());
+            if let Some(extension) = extension {
+                // The on-disk name can differ from the raw title (sanitized
+                // characters, truncation, `-suffix` collision
+                // disambiguation); use the persisted filename so we delete
+                // the file that's actually there.
+                let base_name = filename.unwrap_or_else(|| sanitize_filename(&title));
+                let mut path = get_podcast_dir();
+                path.push(self.title());
+                path.push(format!("{}{}", base_name, extension));
+                if let Err(err) = fs::remove_file(&path) {
+                    eprintln!("{}", err);
+                }
+            }
+            if let Err(err) = db.mark_deleted(&guid) {
+                eprintln!("{}", err);
+            }
+        }
+    }
+}
+
+/// A progress update from one in-flight download, sent from a worker
+/// thread to the `Downloader` coordinator.
+enum DownloadEvent {
+    Started { index: usize, title: String },
+    Progress { index: usize, done: u64, total: u64 },
+    Completed { index: usize, guid: String },
+    Failed { index: usize, guid: String, title: String, err: String },
+}
+
+/// Runs episode downloads across a fixed-size worker pool instead of
+/// rayon's unbounded `par_iter`, reporting per-episode progress over an
+/// `mpsc` channel and rendering it as a `MultiProgress` with one bar per
+/// active download plus an overall bar.
+pub struct Downloader {
+    max_concurrent: usize,
+}
+
+impl Downloader {
+    pub fn new(max_concurrent: usize) -> Downloader {
+        Downloader {
+            max_concurrent: if max_concurrent == 0 { 1 } else { max_concurrent },
+        }
+    }
+
+    /// Download `episodes` into `podcast_name`, marking each as downloaded
+    /// in `db` (if given) as it completes, and returning the `(title, err)`
+    /// pairs of any that failed.
+    pub fn run(
+        &self,
+        podcast_name: &str,
+        episodes: Vec<Episode>,
+        db: Option<&Database>,
+    ) -> Vec<(String, String)> {
+        let total = episodes.len();
+        if total == 0 {
+            return Vec::new();
+        }
+
+        let queue: VecDeque<(usize, Episode)> = episodes.into_iter().enumerate().collect();
+        let queue = Arc::new(Mutex::new(queue));
+        let (tx, rx) = mpsc::channel();
+
+        let workers: Vec<_> = (0..self.max_concurrent)
+            .map(|_| {
+                let queue = Arc::clone(&queue);
+                let tx = tx.clone();
+                let podcast_name = String::from(podcast_name);
+                thread::spawn(move || loop {
+                    let next = queue.lock().unwrap().pop_front();
+                    let (index, episode) = match next {
+                        Some(val) => val,
+                        None => break,
+                    };
+                    let title = String::from(episode.title().unwrap_or(""));
+                    let guid = episode.guid();
+                    tx.send(DownloadEvent::Started {
+                        index,
+                        title: title.clone(),
+                    }).ok();
+                    let result = episode.download_tracked(&podcast_name, index, &tx);
+                    match result {
+                        Ok(()) => {
+                            tx.send(DownloadEvent::Completed { index, guid }).ok();
+                        }
+                        Err(err) => {
+                            tx.send(DownloadEvent::Failed {
+                                index,
+                                guid,
+                                title,
+                                err: format!("{}", err),
+                            }).ok();
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(tx);
+
+        let multi = MultiProgress::new();
+        let overall = multi.add(ProgressBar::new(total as u64));
+        overall.set_style(ProgressStyle::default_bar().template(
+            "overall [{bar:40.green/white}] {pos}/{len}",
+        ).progress_chars("=> "));
+
+        let mut bars = HashMap::new();
+        let mut failures = Vec::new();
+
+        for event in rx {
+            match event {
+                DownloadEvent::Started { index, title } => {
+                    let bar = multi.add(ProgressBar::new(0));
+                    bar.set_style(ProgressStyle::default_bar().template(
+                        "{msg} [{bar:40.cyan/blue}] {bytes}/{total_bytes}",
+                    ).progress_chars("=> "));
+                    bar.set_message(&title);
+                    bars.insert(index, bar);
+                }
+                DownloadEvent::Progress { index, done, total } => {
+                    if let Some(bar) = bars.get(&index) {
+                        bar.set_length(total);
+                        bar.set_position(done);
                     }
                 }
-            },
-        );
+                DownloadEvent::Completed { index, guid } => {
+                    if let Some(bar) = bars.remove(&index) {
+                        bar.finish_with_message("done");
+                    }
+                    if let Some(db) = db {
+                        if let Err(err) = db.mark_downloaded(podcast_name, &guid) {
+                            eprintln!("{}", err);
+                        }
+                    }
+                    overall.inc(1);
+                }
+                DownloadEvent::Failed { index, title, err, .. } => {
+                    if let Some(bar) = bars.remove(&index) {
+                        bar.finish_with_message("failed");
+                    }
+                    failures.push((title, err));
+                    overall.inc(1);
+                }
+            }
+        }
+        overall.finish();
+
+        for worker in workers {
+            if let Err(panic) = worker.join() {
+                let msg = panic_message(&panic);
+                eprintln!("Download worker thread panicked: {}", msg);
+                failures.push((String::from("<unknown>"), format!("worker thread panicked: {}", msg)));
+            }
+        }
+
+        failures
+    }
+}
+
+/// Best-effort extraction of a human-readable message from a thread panic
+/// payload, for reporting a crashed download worker the same way as an
+/// ordinary download failure instead of silently dropping it.
+fn panic_message(payload: &Box<dyn std::any::Any + Send>) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        String::from("unknown panic")
     }
 }
 
@@ -224,6 +893,34 @@ impl Episode {
         self.0.title()
     }
 
+    /// A stable identity for this episode: its RSS `guid` if the feed sets
+    /// one, falling back to the enclosure URL for feeds that don't.
+    pub fn guid(&self) -> String {
+        match self.0.guid() {
+            Some(guid) => String::from(guid.value()),
+            None => self.url().map(String::from).unwrap_or_default(),
+        }
+    }
+
+    /// The on-disk filename (without extension) for this episode: reused
+    /// from a prior run if one was already reserved, otherwise the
+    /// sanitized title, disambiguated against any other episode of this
+    /// podcast that sanitizes down to the same name.
+    fn resolve_filename(&self, podcast_title: &str, title: &str) -> String {
+        let sanitized = sanitize_filename(title);
+        let db = match Database::new() {
+            Ok(db) => db,
+            Err(_) => return sanitized,
+        };
+        let guid = self.guid();
+        if let Some(existing) = db.filename_for(podcast_title, &guid) {
+            return existing;
+        }
+        let suffix = disambiguating_suffix(&guid, self.pub_date().unwrap_or(""));
+        db.reserve_filename(podcast_title, &guid, &sanitized, &suffix)
+            .unwrap_or(sanitized)
+    }
+
     pub fn url(&self) -> Option<&str> {
         match self.0.enclosure() {
             Some(val) => Some(val.url()),
@@ -231,37 +928,185 @@ impl Episode {
         }
     }
 
-    pub fn extension(&self) -> Option<&str> {
+    pub fn pub_date(&self) -> Option<&str> {
+        self.0.pub_date()
+    }
+
+    /// Whether the enclosure declares a `audio/*` MIME type, i.e. is
+    /// unambiguously direct audio and doesn't need probing or `yt-dlp`.
+    fn mime_is_audio(&self) -> bool {
+        self.0
+            .enclosure()
+            .map(|enclosure| enclosure.mime_type().starts_with("audio/"))
+            .unwrap_or(false)
+    }
+
+    pub fn extension(&self) -> Option<String> {
         match self.0.enclosure() {
             Some(enclosure) => {
                 match enclosure.mime_type() {
-                    "audio/mpeg" => Some(".mp3"),
-                    "audio/mp4" => Some(".m4a"),
-                    "audio/ogg" => Some(".ogg"),
-                    _ => find_extension(self.url().unwrap()),
+                    "audio/mpeg" => Some(String::from(".mp3")),
+                    "audio/mp4" => Some(String::from(".m4a")),
+                    "audio/ogg" => Some(String::from(".ogg")),
+                    _ => find_extension(self.url().unwrap())
+                        .map(String::from)
+                        .or_else(|| self.ytdlp_extension()),
                 }
             }
             None => None,
         }
     }
 
+    /// Best-audio extension resolved via the optional `yt-dlp` backend,
+    /// for enclosures whose MIME type `find_extension` can't place.
+    #[cfg(feature = "ytdlp")]
+    fn ytdlp_extension(&self) -> Option<String> {
+        ytdlp_lookup(self.url()?, "bestaudio").map(|info| format!(".{}", info.ext))
+    }
+
+    #[cfg(not(feature = "ytdlp"))]
+    fn ytdlp_extension(&self) -> Option<String> {
+        None
+    }
+
+    /// Resolve the real source URL and extension for an enclosure whose
+    /// declared MIME type didn't already identify it as direct audio: probe
+    /// its actual `Content-Type` and, if that doesn't look like audio
+    /// either, resolve both the URL and extension from a single `yt-dlp`
+    /// lookup rather than letting `extension()`'s own fallback shell out to
+    /// `yt-dlp` a second time just to re-derive the extension.
+    #[cfg(feature = "ytdlp")]
+    fn resolve_ambiguous_source(
+        &self,
+        client: &reqwest::Client,
+        url: &str,
+    ) -> Result<(String, String), io::Error> {
+        let probe = client
+            .get(url)
+            .send()
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        if !is_audio_response(&probe) {
+            if let Some(info) = ytdlp_lookup(url, "bestaudio") {
+                return Ok((info.url, format!(".{}", info.ext)));
+            }
+        }
+        Ok((String::from(url), self.extension().unwrap()))
+    }
+
+    #[cfg(not(feature = "ytdlp"))]
+    fn resolve_ambiguous_source(
+        &self,
+        _client: &reqwest::Client,
+        url: &str,
+    ) -> Result<(String, String), io::Error> {
+        Ok((String::from(url), self.extension().unwrap()))
+    }
 
     pub fn download(&self, podcast_name: &str) -> Result<(), io::Error> {
+        let pb = ProgressBar::new(0);
+        pb.set_style(ProgressStyle::default_bar().template(
+            "{msg} [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})",
+        ).progress_chars("=> "));
+        pb.set_message(self.title().unwrap_or(""));
+
+        let result = self.download_core(podcast_name, |done, total| {
+            if pb.length() != total {
+                pb.set_length(total);
+            }
+            pb.set_position(done);
+        });
+        match result {
+            Ok(()) => pb.finish_with_message("done"),
+            Err(_) => pb.finish_with_message("failed"),
+        }
+        result
+    }
+
+    /// Like `download`, but reports `Progress` to `tx` instead of
+    /// drawing its own progress bar, for use under a `Downloader` that owns
+    /// a shared `MultiProgress`.
+    fn download_tracked(
+        &self,
+        podcast_name: &str,
+        index: usize,
+        tx: &mpsc::Sender<DownloadEvent>,
+    ) -> Result<(), io::Error> {
+        self.download_core(podcast_name, |done, total| {
+            tx.send(DownloadEvent::Progress { index, done, total }).ok();
+        })
+    }
+
+    fn download_core<F: FnMut(u64, u64)>(
+        &self,
+        podcast_name: &str,
+        mut on_progress: F,
+    ) -> Result<(), io::Error> {
         let mut path = get_podcast_dir();
         path.push(podcast_name);
         DirBuilder::new().recursive(true).create(&path).unwrap();
 
         if let Some(url) = self.url() {
             if let Some(title) = self.title() {
-                let mut filename = String::from(title);
-                filename.push_str(self.extension().unwrap());
-                path.push(filename);
+                let client = reqwest::Client::new();
+                // Only probe (and potentially shell out to `yt-dlp`) when
+                // the enclosure's declared MIME type is actually ambiguous;
+                // a declared `audio/*` type is unambiguously direct audio
+                // and doesn't need the extra un-ranged request.
+                let (source_url, extension) = if self.mime_is_audio() {
+                    (String::from(url), self.extension().unwrap())
+                } else {
+                    self.resolve_ambiguous_source(&client, url)?
+                };
+
+                let base_name = self.resolve_filename(podcast_name, title);
+                path.push(format!("{}{}", base_name, extension));
+
+                let mut part_path = path.clone();
+                part_path.set_file_name(format!(
+                    "{}.part",
+                    path.file_name().unwrap().to_str().unwrap()
+                ));
+
+                let existing_len = fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+
                 println!("Downloading: {}", path.to_str().unwrap());
-                let mut file = File::create(&path)?;
-                let mut resp = reqwest::get(url).unwrap();
-                let mut content: Vec<u8> = Vec::new();
-                resp.read_to_end(&mut content)?;
-                file.write_all(&content)?;
+                let mut req = client.get(&source_url);
+                if existing_len > 0 {
+                    req = req.header(Range::Bytes(vec![ByteRangeSpec::AllFrom(existing_len)]));
+                }
+                let mut resp = req
+                    .send()
+                    .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+                let (mut file, resume_from) = if resp.status() == StatusCode::PartialContent {
+                    (
+                        OpenOptions::new().append(true).open(&part_path)?,
+                        existing_len,
+                    )
+                } else {
+                    (File::create(&part_path)?, 0)
+                };
+
+                let total = resp.headers()
+                    .get::<ContentLength>()
+                    .map(|len| **len + resume_from)
+                    .unwrap_or(0);
+
+                on_progress(resume_from, total);
+
+                let mut buf = [0u8; 8192];
+                let mut done = resume_from;
+                loop {
+                    let n = resp.read(&mut buf)?;
+                    if n == 0 {
+                        break;
+                    }
+                    file.write_all(&buf[..n])?;
+                    done += n as u64;
+                    on_progress(done, total);
+                }
+
+                fs::rename(&part_path, &path)?;
                 return Ok(());
             }
         }